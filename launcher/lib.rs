@@ -1,12 +1,26 @@
-/// Launcher for rit subcommands.
+//! Launcher for rit subcommands.
 
 #[macro_use]
 extern crate quick_error;
 
 use std::error::Error;
+use std::fmt;
 use std::io::ErrorKind;
 use std::process;
 
+/// Conventional process exit codes, so `rit` exits the way the underlying tool (or the shell,
+/// for a signal-terminated child) would have.
+pub mod exit_codes {
+    /// A generic failure not covered by a more specific code below.
+    pub const UNKNOWN_ERROR: i32 = 1;
+    /// No launcher could run the command: it wasn't found, was blacklisted, or isn't implemented
+    /// natively. Matches the shell's "command not found" convention.
+    pub const UNSUPPORTED: i32 = 127;
+    /// Added to a signal number when a child was terminated by a signal, matching the shell
+    /// convention of reporting such exits as `128 + signum`.
+    pub const SIGNALED_OFFSET: i32 = 128;
+}
+
 quick_error! {
     #[derive(Debug)]
     pub enum LaunchFailed {
@@ -19,14 +33,127 @@ quick_error! {
             description("This command is blacklisted from this launcher")
             display(r#"The command "{}" is blacklisted from this launcher"#, name)
         }
-        BadExitCode(name: String, status: process::ExitStatus) {
+        BadExitCode(name: String, status: process::ExitStatus, output: Option<CmdOut>) {
             description("The command ran, but returned a code indicating failure")
-            display(r#"The command "{}" {}."#, name, match status.code() {
-                Some(code) => format!("returned error code {}", code),
-                // TODO: conditionally include std::os::unix & get signal name here
-                None => "was terminated by a signal".to_string(),
+            display("{}", match output {
+                Some(out) => format!("{}", out),
+                None => format!(r#"The command "{}" {}."#, name, match status.code() {
+                    Some(code) => format!("returned error code {}", code),
+                    None => describe_signal(*status),
+                }),
             })
         }
+        Unimplemented(name: String) {
+            description("This command is not implemented natively by rit")
+            display(r#"The command "{}" is not implemented natively by rit"#, name)
+        }
+        AllLaunchersFailed(errors: Vec<Box<dyn Error>>) {
+            description("No launcher was able to run this command")
+            display("no launcher could run this command:\n{}", errors
+                .iter()
+                .map(|e| format!("  - {}", e))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+    }
+}
+
+impl LaunchFailed {
+    /// Whether this error means the launcher simply can't handle the command (so a fallback
+    /// launcher should try the next one), as opposed to the command having actually run and
+    /// failed (in which case falling back further would be misleading).
+    pub fn is_unsupported(&self) -> bool {
+        matches!(
+            self,
+            LaunchFailed::NotFound(_) | LaunchFailed::Blacklisted(_) | LaunchFailed::Unimplemented(_)
+        )
+    }
+
+    /// The process exit code rit should use to reflect this failure, preferring the underlying
+    /// subcommand's own status over a blanket failure code.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LaunchFailed::NotFound(_) | LaunchFailed::Blacklisted(_) | LaunchFailed::Unimplemented(_) => {
+                exit_codes::UNSUPPORTED
+            }
+            LaunchFailed::BadExitCode(_, status, _) => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::process::ExitStatusExt;
+                    if let Some(sig) = status.signal() {
+                        return exit_codes::SIGNALED_OFFSET + sig;
+                    }
+                }
+                status.code().unwrap_or(exit_codes::UNKNOWN_ERROR)
+            }
+            LaunchFailed::AllLaunchersFailed(errors) => errors
+                .iter()
+                .find_map(|e| e.downcast_ref::<LaunchFailed>().map(LaunchFailed::exit_code))
+                .unwrap_or(exit_codes::UNKNOWN_ERROR),
+        }
+    }
+}
+
+/// The captured stdout/stderr of a subcommand invocation, kept around so a failure can be
+/// rendered with the actual command and its diagnostics rather than just an exit code.
+#[derive(Debug)]
+pub struct CmdOut {
+    pub command: String,
+    pub args: Vec<String>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub status: process::ExitStatus,
+}
+
+impl fmt::Display for CmdOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "$ {}", self.command)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        let stderr = String::from_utf8_lossy(&self.stderr);
+        let tail = stderr.lines().rev().take(10).collect::<Vec<_>>();
+        if !tail.is_empty() {
+            write!(f, "\n{}", tail.into_iter().rev().collect::<Vec<_>>().join("\n"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps a spawn-time `io::Error` to the corresponding `LaunchFailed`, shared by launchers that
+/// invoke `name` as a subprocess.
+fn map_spawn_error(name: &str, e: std::io::Error) -> Box<dyn Error> {
+    if e.kind() == ErrorKind::NotFound {
+        Box::new(LaunchFailed::NotFound(name.to_string()))
+    } else {
+        Box::new(e)
+    }
+}
+
+/// Describes a child that was terminated by a signal, naming the signal where possible.
+#[cfg(unix)]
+fn describe_signal(status: process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match status.signal() {
+        Some(sig) => format!("was killed by {}", signal_name(sig)),
+        None => "was terminated by a signal".to_string(),
+    }
+}
+
+#[cfg(not(unix))]
+fn describe_signal(_status: process::ExitStatus) -> String {
+    "was terminated by a signal".to_string()
+}
+
+#[cfg(unix)]
+fn signal_name(sig: i32) -> String {
+    match sig {
+        libc::SIGINT => "SIGINT".to_string(),
+        libc::SIGTERM => "SIGTERM".to_string(),
+        libc::SIGHUP => "SIGHUP".to_string(),
+        libc::SIGQUIT => "SIGQUIT".to_string(),
+        libc::SIGKILL => "SIGKILL".to_string(),
+        other => format!("signal {}", other),
     }
 }
 
@@ -38,40 +165,231 @@ pub trait RitLauncher {
 pub struct ProcLauncher<'a> {
     /// Name of the base command, nominally rit or git.
     cmd_name: &'a str,
+    /// If true, captures the child's stdout/stderr instead of inheriting them, attaching a
+    /// `CmdOut` to `LaunchFailed::BadExitCode` on failure so diagnostics can be rendered later.
+    capture_output: bool,
 }
 
 impl<'a> RitLauncher for ProcLauncher<'a> {
     fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
         // note: to be closer to git's behavior we could use libc::execv() here
-        match process::Command::new(self.cmd_name)
+        let mut command = process::Command::new(self.cmd_name);
+        command.arg(name).args(args);
+
+        if self.capture_output {
+            match command.output() {
+                Ok(output) if output.status.code() == Some(0) => {
+                    // Command::output() doesn't stream to our own stdout/stderr, so we have to
+                    // forward what we captured ourselves or a successful run would look silent.
+                    use std::io::Write;
+                    std::io::stdout().write_all(&output.stdout)?;
+                    std::io::stderr().write_all(&output.stderr)?;
+                    Ok(())
+                }
+                Ok(output) => Err(Box::new(LaunchFailed::BadExitCode(
+                    name.to_string(),
+                    output.status,
+                    Some(CmdOut {
+                        command: self.cmd_name.to_string(),
+                        args: std::iter::once(name.to_string())
+                            .chain(args.iter().cloned())
+                            .collect(),
+                        stdout: output.stdout,
+                        stderr: output.stderr,
+                        status: output.status,
+                    }),
+                ))),
+                Err(e) => Err(map_spawn_error(name, e)),
+            }
+        } else {
+            match command.status() {
+                Ok(status) if status.code() == Some(0) => Ok(()),
+                Ok(status) => Err(Box::new(LaunchFailed::BadExitCode(
+                    name.to_string(),
+                    status,
+                    None,
+                ))),
+                Err(e) => Err(map_spawn_error(name, e)),
+            }
+        }
+    }
+}
+
+/// Launches rit subcommands by replacing the current process image.
+///
+/// On Unix this uses `exec()`, matching how `rhg` (Mercurial's Rust front-end) dispatches to the
+/// real `hg` binary: signal handling, job control, and exit-code semantics all become
+/// transparent, since the shell ends up talking to the real subprocess directly instead of to
+/// `rit`. On non-Unix targets, where there is no equivalent, this falls back to the
+/// spawn-and-wait behavior of `ProcLauncher`.
+pub struct ExecLauncher<'a> {
+    /// Name of the base command, nominally rit or git.
+    cmd_name: &'a str,
+}
+
+#[cfg(unix)]
+impl<'a> RitLauncher for ExecLauncher<'a> {
+    fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+        use std::os::unix::process::CommandExt;
+
+        // exec() only returns here on error; on success the current process image is gone.
+        let err = process::Command::new(self.cmd_name)
+            .arg(name)
+            .args(args)
+            .exec();
+        if err.kind() == ErrorKind::NotFound {
+            Err(Box::new(LaunchFailed::NotFound(name.to_string())))
+        } else {
+            Err(Box::new(err))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl<'a> RitLauncher for ExecLauncher<'a> {
+    fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+        ProcLauncher {
+            cmd_name: self.cmd_name,
+            capture_output: false,
+        }
+        .launch(name, args)
+    }
+}
+
+/// Launches rit subcommands in their own process group, forwarding SIGINT/SIGTERM/SIGHUP to that
+/// group so interactive children (pagers, editors spawned by `git commit`, long clones) get clean
+/// Ctrl-C semantics instead of the signal only reaching `rit` itself.
+///
+/// This mirrors watchexec's approach: giving the child its own process group means a signal sent
+/// to that group reaches every process the child spawns, not just the immediate child. Because a
+/// background process group can't read/write a controlling terminal without being stopped by
+/// SIGTTIN/SIGTTOU, this also hands the terminal to the child's group for the duration of the
+/// call (the classic shell job-control dance), so pagers and editors keep working normally. When
+/// stdin isn't a controlling terminal the handoff is a harmless no-op.
+pub struct SignalForwardingLauncher<'a> {
+    /// Name of the base command, nominally rit or git.
+    cmd_name: &'a str,
+}
+
+/// Makes `pgid` the controlling terminal's foreground process group, ignoring SIGTTOU around the
+/// call so we aren't stopped by our own attempt to do it. A no-op if stdin has no controlling
+/// terminal.
+#[cfg(unix)]
+fn set_foreground_process_group(pgid: libc::pid_t) {
+    unsafe {
+        let prev_handler = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+        libc::signal(libc::SIGTTOU, prev_handler);
+    }
+}
+
+#[cfg(unix)]
+impl<'a> RitLauncher for SignalForwardingLauncher<'a> {
+    fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+        use std::os::unix::process::CommandExt;
+
+        let mut child = match process::Command::new(self.cmd_name)
             .arg(name)
             .args(args)
-            .status()
+            .process_group(0)
+            .spawn()
         {
+            Ok(child) => child,
+            Err(e) => return Err(map_spawn_error(name, e)),
+        };
+
+        let pgid = child.id() as i32;
+        set_foreground_process_group(pgid);
+
+        let mut signals = signal_hook::iterator::Signals::new([
+            signal_hook::consts::SIGINT,
+            signal_hook::consts::SIGTERM,
+            signal_hook::consts::SIGHUP,
+        ])?;
+        let handle = signals.handle();
+        let forwarding = std::thread::spawn(move || {
+            for sig in signals.forever() {
+                // Negative pid targets the whole process group.
+                unsafe {
+                    libc::kill(-pgid, sig);
+                }
+            }
+        });
+
+        let status = child.wait();
+        handle.close();
+        let _ = forwarding.join();
+
+        // Take the terminal back now that the child is gone.
+        set_foreground_process_group(unsafe { libc::getpgrp() });
+
+        match status {
             Ok(status) if status.code() == Some(0) => Ok(()),
             Ok(status) => Err(Box::new(LaunchFailed::BadExitCode(
                 name.to_string(),
                 status,
+                None,
             ))),
-            Err(e) => {
-                if e.kind() == ErrorKind::NotFound {
-                    Err(Box::new(LaunchFailed::NotFound(name.to_string())))
-                } else {
-                    Err(Box::new(e))
-                }
-            }
+            Err(e) => Err(map_spawn_error(name, e)),
+        }
+    }
+}
+
+// Windows already forwards Ctrl-C to every process attached to the same console (unless a child
+// was created with CREATE_NEW_PROCESS_GROUP, which we don't do here), so there is no equivalent
+// forwarding step to add there; just spawn and wait like `ProcLauncher`.
+#[cfg(not(unix))]
+impl<'a> RitLauncher for SignalForwardingLauncher<'a> {
+    fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
+        ProcLauncher {
+            cmd_name: self.cmd_name,
+            capture_output: false,
         }
+        .launch(name, args)
+    }
+}
+
+/// A rit subcommand implemented natively, in-process, as opposed to by calling out to git.
+pub trait Subcommand {
+    fn run(&self, args: &[String]) -> Result<(), Box<dyn Error>>;
+}
+
+struct HelpSubcommand;
+
+impl Subcommand for HelpSubcommand {
+    fn run(&self, _args: &[String]) -> Result<(), Box<dyn Error>> {
+        println!("rit: a native git frontend. Run `git help` for command documentation.");
+        Ok(())
     }
 }
 
 /// Launches rit subcommands by calling their run() function. If a function is actually implemented
 /// in rit (as opposed to actually calling a git subcommand), this launcher should be preferred.
-pub struct LibLauncher;
+pub struct LibLauncher {
+    subcommands: std::collections::HashMap<&'static str, Box<dyn Subcommand>>,
+}
+
+impl LibLauncher {
+    pub fn new() -> Self {
+        let mut subcommands: std::collections::HashMap<&'static str, Box<dyn Subcommand>> =
+            std::collections::HashMap::new();
+        subcommands.insert("help", Box::new(HelpSubcommand));
+        LibLauncher { subcommands }
+    }
+}
+
+impl Default for LibLauncher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl RitLauncher for LibLauncher {
     fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
-        // TODO: actually launch stuff
-        Ok(())
+        match self.subcommands.get(name) {
+            Some(subcommand) => subcommand.run(args),
+            None => Err(Box::new(LaunchFailed::Unimplemented(name.to_string()))),
+        }
     }
 }
 
@@ -90,7 +408,7 @@ impl RitLauncher for BlacklistLauncher {
                 return Err(Box::new(LaunchFailed::Blacklisted(forbidden_name)));
             }
         }
-        return self.launcher.launch(name, args);
+        self.launcher.launch(name, args)
     }
 }
 
@@ -101,28 +419,47 @@ pub struct FallbackLauncher {
 
 impl RitLauncher for FallbackLauncher {
     fn launch(&self, name: &str, args: &[String]) -> Result<(), Box<dyn Error>> {
-        let (last, firsts) = self
-            .launchers
-            .split_last()
-            .expect("no launchers given to FallbackLauncher");
-        for launcher in firsts.iter() {
-            if let Ok(x) = launcher.launch(name, &args) {
-                return Ok(x);
+        if self.launchers.is_empty() {
+            panic!("no launchers given to FallbackLauncher");
+        }
+        let mut errors = Vec::new();
+        for launcher in self.launchers.iter() {
+            match launcher.launch(name, args) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    // Only keep trying other launchers if this one genuinely can't handle the
+                    // command; a command that ran and failed should surface that failure instead
+                    // of silently cascading into the next launcher.
+                    let unsupported = e
+                        .downcast_ref::<LaunchFailed>()
+                        .is_some_and(LaunchFailed::is_unsupported);
+                    if !unsupported {
+                        return Err(e);
+                    }
+                    errors.push(e);
+                }
             }
         }
-        return last.launch(name, args);
+        Err(Box::new(LaunchFailed::AllLaunchersFailed(errors)))
     }
 }
 
 pub fn get_default_launcher() -> impl RitLauncher {
     FallbackLauncher {
         launchers: vec![
+            // LibLauncher must come first: it only fails with Unimplemented for commands it
+            // doesn't know, which is unsupported and falls through, whereas git's own "unknown
+            // subcommand" exit code is a terminal BadExitCode. Trying git first would mean native
+            // commands never get a chance to run.
+            Box::new(LibLauncher::new()),
             Box::new(BlacklistLauncher {
-                launcher: Box::new(ProcLauncher { cmd_name: "git" }),
+                launcher: Box::new(ProcLauncher {
+                    cmd_name: "git",
+                    capture_output: false,
+                }),
                 // git help is part of the main launcher command for the OG git
                 blacklist: &["help"],
             }),
-            Box::new(LibLauncher {}),
         ],
     }
 }
@@ -140,7 +477,11 @@ mod tests {
 
     enum Should {
         Succeed,
-        Fail,
+        // Fails in a way that looks like "this launcher can't handle it" (should fall back).
+        FailUnsupported,
+        // Fails in a way that looks like "this launcher ran it and it failed" (should not fall
+        // back any further).
+        FailTerminal,
     }
 
     struct DummyLauncher {
@@ -151,14 +492,18 @@ mod tests {
         fn launch(&self, _name: &str, _args: &[String]) -> Result<(), Box<dyn Error>> {
             match &self.always {
                 Should::Succeed => Ok(()),
-                Should::Fail => Err(Box::new(TestingErrors::DummyError {})),
+                Should::FailUnsupported => Err(Box::new(LaunchFailed::NotFound("dummy".to_string()))),
+                Should::FailTerminal => Err(Box::new(TestingErrors::DummyError {})),
             }
         }
     }
 
     #[test]
     fn proclauncher_launches_processes() {
-        let launcher = ProcLauncher { cmd_name: "true" };
+        let launcher = ProcLauncher {
+            cmd_name: "true",
+            capture_output: false,
+        };
         assert!(launcher.launch("whatever", &[]).is_ok());
     }
 
@@ -166,19 +511,86 @@ mod tests {
     fn proclauncher_fails_on_nonexistent() {
         let launcher = ProcLauncher {
             cmd_name: "not-a-real-command",
+            capture_output: false,
         };
         assert!(launcher.launch("this-shouldnt-exist", &[]).is_err());
     }
 
     #[test]
-    fn liblauncher_launches_libs() {
-        let launcher = LibLauncher {};
-        assert!(launcher.launch("test", &[]).is_ok());
+    fn proclauncher_captures_output_on_failure() {
+        let launcher = ProcLauncher {
+            cmd_name: "false",
+            capture_output: true,
+        };
+        let err = launcher.launch("whatever", &[]).unwrap_err();
+        let failure = err
+            .downcast_ref::<LaunchFailed>()
+            .expect("should be a LaunchFailed");
+        match failure {
+            LaunchFailed::BadExitCode(_, _, output) => assert!(output.is_some()),
+            other => panic!("expected BadExitCode, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn exit_code_reflects_unsupported_commands() {
+        assert_eq!(
+            LaunchFailed::NotFound("whatever".to_string()).exit_code(),
+            exit_codes::UNSUPPORTED
+        );
+        assert_eq!(
+            LaunchFailed::Unimplemented("whatever".to_string()).exit_code(),
+            exit_codes::UNSUPPORTED
+        );
+    }
+
+    #[test]
+    fn exit_code_reflects_child_status() {
+        let launcher = ProcLauncher {
+            cmd_name: "false",
+            capture_output: false,
+        };
+        let err = launcher.launch("whatever", &[]).unwrap_err();
+        let failure = err.downcast_ref::<LaunchFailed>().expect("should be a LaunchFailed");
+        assert_eq!(failure.exit_code(), 1);
+    }
+
+    // ExecLauncher replaces the current process on success, so only its failure path is testable
+    // here without tearing down the test binary itself.
+    #[cfg(unix)]
+    #[test]
+    fn execlauncher_fails_on_nonexistent() {
+        let launcher = ExecLauncher {
+            cmd_name: "not-a-real-command",
+        };
+        assert!(launcher.launch("this-shouldnt-exist", &[]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signalforwardinglauncher_launches_processes() {
+        let launcher = SignalForwardingLauncher { cmd_name: "true" };
+        assert!(launcher.launch("whatever", &[]).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signalforwardinglauncher_fails_on_nonexistent() {
+        let launcher = SignalForwardingLauncher {
+            cmd_name: "not-a-real-command",
+        };
+        assert!(launcher.launch("this-shouldnt-exist", &[]).is_err());
+    }
+
+    #[test]
+    fn liblauncher_launches_registered_subcommands() {
+        let launcher = LibLauncher::new();
+        assert!(launcher.launch("help", &[]).is_ok());
     }
 
     #[test]
     fn liblauncher_fails_on_nonexistent() {
-        let launcher = LibLauncher {};
+        let launcher = LibLauncher::new();
         assert!(launcher.launch("this-shouldnt-exist", &[]).is_err());
     }
 
@@ -212,11 +624,11 @@ mod tests {
     }
 
     #[test]
-    fn fallbacklauncher_falls_back() {
+    fn fallbacklauncher_falls_back_on_unsupported() {
         let launcher = FallbackLauncher {
             launchers: vec![
                 Box::new(DummyLauncher {
-                    always: Should::Fail,
+                    always: Should::FailUnsupported,
                 }),
                 Box::new(DummyLauncher {
                     always: Should::Succeed,
@@ -226,21 +638,59 @@ mod tests {
         assert!(launcher.launch("whatever", &[]).is_ok());
     }
 
+    #[test]
+    fn fallbacklauncher_does_not_fall_back_on_terminal_failure() {
+        let launcher = FallbackLauncher {
+            launchers: vec![
+                Box::new(DummyLauncher {
+                    always: Should::FailTerminal,
+                }),
+                Box::new(DummyLauncher {
+                    always: Should::Succeed,
+                }),
+            ],
+        };
+        assert!(launcher.launch("whatever", &[]).is_err());
+    }
+
     #[test]
     fn fallbacklauncher_ultimately_fails() {
         let launcher = FallbackLauncher {
             launchers: vec![
                 Box::new(DummyLauncher {
-                    always: Should::Fail,
+                    always: Should::FailUnsupported,
                 }),
                 Box::new(DummyLauncher {
-                    always: Should::Fail,
+                    always: Should::FailUnsupported,
                 }),
             ],
         };
         assert!(launcher.launch("whatever", &[]).is_err());
     }
 
+    #[test]
+    fn fallbacklauncher_aggregates_errors() {
+        let launcher = FallbackLauncher {
+            launchers: vec![
+                Box::new(DummyLauncher {
+                    always: Should::FailUnsupported,
+                }),
+                Box::new(DummyLauncher {
+                    always: Should::FailUnsupported,
+                }),
+                Box::new(DummyLauncher {
+                    always: Should::FailUnsupported,
+                }),
+            ],
+        };
+        let err = launcher.launch("whatever", &[]).unwrap_err();
+        let failure = err.downcast_ref::<LaunchFailed>().expect("should be a LaunchFailed");
+        match failure {
+            LaunchFailed::AllLaunchersFailed(errors) => assert_eq!(errors.len(), 3),
+            other => panic!("expected AllLaunchersFailed, got {:?}", other),
+        }
+    }
+
     #[test]
     fn dummy_launcher_combination_works() {
         let launcher = FallbackLauncher {
@@ -251,7 +701,7 @@ mod tests {
                     }),
                     blacklist: &["help"],
                 }),
-                Box::new(LibLauncher {}),
+                Box::new(LibLauncher::new()),
             ],
         };
         assert!(launcher.launch("status", &[]).is_ok());
@@ -1,11 +1,15 @@
 extern crate rit_launcher;
 
-use rit_launcher::RitLauncher;
+use rit_launcher::{exit_codes, LaunchFailed, RitLauncher};
 use std::process;
 
 fn main() {
     if let Err(e) = rit_launcher::get_default_launcher().launch("status", &[]) {
         eprintln!("error: {}", e);
-        process::exit(1);
+        let code = e
+            .downcast_ref::<LaunchFailed>()
+            .map(LaunchFailed::exit_code)
+            .unwrap_or(exit_codes::UNKNOWN_ERROR);
+        process::exit(code);
     }
 }